@@ -0,0 +1,93 @@
+use crate::Node;
+
+/// Folds `CONC`/`CONT` children into their parent node's `data`, the way
+/// a GEDCOM reader is supposed to: `CONC` appends its value with no
+/// separator, `CONT` appends it after a newline. Runs after the tree is
+/// built so multi-line NOTE/TEXT values end up as a single `data` field
+/// instead of a run of sibling nodes.
+pub fn fold_tree(tree: &mut [Node]) {
+  for node in tree.iter_mut() {
+    fold_node(node);
+  }
+}
+
+fn fold_node(node: &mut Node) {
+  let mut folded = Vec::with_capacity(node.children.len());
+
+  for mut child in std::mem::take(&mut node.children) {
+    // Fold the child's own continuations into it first. CONC/CONT are
+    // always supposed to be flat siblings, never nested under each
+    // other, but folding bottom-up first means a malformed or
+    // out-of-spec nesting still merges in full instead of silently
+    // dropping data.
+    fold_node(&mut child);
+
+    match child.tag.as_str() {
+      "CONC" => node.data.push_str(&child.data),
+      "CONT" => {
+        node.data.push('\n');
+        node.data.push_str(&child.data);
+      }
+      _ => folded.push(child),
+    }
+  }
+
+  node.children = folded;
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn conc_appends_with_no_separator() {
+    let mut note = Node::new(1, "NOTE", "Hello", "", 1, 0);
+    note.children.push(Node::new(2, "CONC", " world", "", 2, 0));
+
+    let mut tree = vec![note];
+    fold_tree(&mut tree);
+
+    assert_eq!(tree[0].data, "Hello world");
+    assert!(tree[0].children.is_empty());
+  }
+
+  #[test]
+  fn cont_appends_after_a_newline() {
+    let mut note = Node::new(1, "NOTE", "Hello", "", 1, 0);
+    note.children.push(Node::new(2, "CONT", "world", "", 2, 0));
+
+    let mut tree = vec![note];
+    fold_tree(&mut tree);
+
+    assert_eq!(tree[0].data, "Hello\nworld");
+  }
+
+  #[test]
+  fn leaves_non_continuation_children_in_place() {
+    let mut indi = Node::new(0, "INDI", "", "@I1@", 1, 0);
+    indi.children.push(Node::new(1, "NAME", "John /Doe/", "", 2, 0));
+
+    let mut tree = vec![indi];
+    fold_tree(&mut tree);
+
+    assert_eq!(tree[0].children.len(), 1);
+    assert_eq!(tree[0].children[0].tag, "NAME");
+  }
+
+  #[test]
+  fn folds_a_continuation_that_itself_has_nested_children() {
+    // CONC/CONT are never supposed to have their own CONC/CONT children,
+    // but folding bottom-up first means a malformed nesting still merges
+    // in full instead of silently dropping data.
+    let mut cont = Node::new(2, "CONT", "world", "", 2, 0);
+    cont.children.push(Node::new(3, "CONC", "!", "", 3, 0));
+
+    let mut note = Node::new(1, "NOTE", "Hello", "", 1, 0);
+    note.children.push(cont);
+
+    let mut tree = vec![note];
+    fold_tree(&mut tree);
+
+    assert_eq!(tree[0].data, "Hello\nworld!");
+  }
+}
@@ -0,0 +1,58 @@
+use serde::Serialize;
+
+/// Severity of a single parse diagnostic, mirroring the levels used by
+/// typical compiler diagnostics.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Level {
+  Error,
+  Warning,
+  Note,
+  Help,
+}
+
+/// A 1-based line/column range identifying where a diagnostic applies.
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct Span {
+  pub line_start: usize,
+  pub column_start: usize,
+  pub line_end: usize,
+  pub column_end: usize,
+}
+
+impl Span {
+  pub fn single_line(line: usize, column_start: usize, column_end: usize) -> Span {
+    Span {
+      line_start: line,
+      column_start,
+      line_end: line,
+      column_end,
+    }
+  }
+}
+
+/// A single structured problem found while parsing a GEDCOM file.
+#[derive(Debug, Clone, Serialize)]
+pub struct Diagnostic {
+  pub level: Level,
+  pub message: String,
+  pub span: Span,
+}
+
+impl Diagnostic {
+  pub fn error(message: impl Into<String>, span: Span) -> Diagnostic {
+    Diagnostic {
+      level: Level::Error,
+      message: message.into(),
+      span,
+    }
+  }
+
+  pub fn warning(message: impl Into<String>, span: Span) -> Diagnostic {
+    Diagnostic {
+      level: Level::Warning,
+      message: message.into(),
+      span,
+    }
+  }
+}
@@ -1,16 +1,30 @@
+#[cfg(not(target_arch = "wasm32"))]
+use rayon::prelude::*;
 use regex::Regex;
-use serde::Serialize;
-use std::cell::RefCell;
+use serde::{Deserialize, Serialize};
 use std::error::Error;
 use std::fs;
 use wasm_bindgen::prelude::*;
 
+mod continuations;
+mod diagnostics;
+mod resolve;
+mod tokenizer;
+mod writer;
+
+pub use diagnostics::{Diagnostic, Level, Span};
+pub use resolve::{ResolvedNode, ResolvedPointer};
+
+use tokenizer::{lex_line, LexError};
+
 // When the `wee_alloc` feature is enabled, use `wee_alloc` as the global
 // allocator.
 #[cfg(feature = "wee_alloc")]
 #[global_allocator]
 static ALLOC: wee_alloc::WeeAlloc = wee_alloc::WeeAlloc::INIT;
 
+type BoxError = Box<dyn Error + Send + Sync>;
+
 pub struct Config {
   pub input_filename: String,
   pub output_filename: String,
@@ -36,31 +50,43 @@ type Tag = String;
 type Data = String;
 type Pointer = String;
 
-#[derive(Debug, Serialize)]
-pub struct Node<'a> {
-  data: Data,
-  tag: Tag,
-  pointer: Pointer,
-  level: i32,
-  children: RefCell<Vec<&'a Node<'a>>>,
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Node {
+  pub(crate) data: Data,
+  pub(crate) tag: Tag,
+  pub(crate) pointer: Pointer,
+  pub(crate) level: i32,
+  #[serde(skip)]
+  pub(crate) line: usize,
+  /// 0-based column `data` started at in the source line, used to give
+  /// diagnostics raised against an already-built node a precise span.
+  #[serde(skip)]
+  pub(crate) column: usize,
+  pub(crate) children: Vec<Node>,
 }
 
-impl<'a> Node<'a> {
-  fn new(level: i32, tag: &str, data: &str, pointer: &str) -> Node<'a> {
+impl Node {
+  fn new(level: i32, tag: &str, data: &str, pointer: &str, line: usize, column: usize) -> Node {
     Node {
       level,
       tag: String::from(tag),
       data: String::from(data),
       pointer: String::from(pointer),
-      children: RefCell::new(vec![]),
+      line,
+      column,
+      children: Vec::new(),
     }
   }
 }
 
-pub fn run(config: Config) -> Result<(), Box<dyn Error>> {
+pub fn run(config: Config) -> Result<(), BoxError> {
   let contents = fs::read_to_string(config.input_filename)?;
 
-  let result = parse_to_json(contents)?;
+  let (result, diagnostics) = parse_to_json(contents)?;
+
+  for diagnostic in &diagnostics {
+    eprintln!("{:?}: {}", diagnostic.level, diagnostic.message);
+  }
 
   fs::write(config.output_filename, result)?;
   Ok(())
@@ -68,78 +94,300 @@ pub fn run(config: Config) -> Result<(), Box<dyn Error>> {
 
 #[wasm_bindgen]
 pub fn parse_to_js(contents: String) -> Result<JsValue, JsValue> {
-  let mut all_nodes = parse_into_nodes(contents).unwrap();
-  let mut dummy_root = Node::new(-1, "dummy", "", "");
-  build_tree(&mut all_nodes, &mut dummy_root).unwrap();
-  let jsObj: JsValue = JsValue::from_serde(&dummy_root.children)
-    .unwrap()
-    .to_owned();
+  let (tree, diagnostics) = parse_to_tree(contents).unwrap();
+
+  let tree_js: JsValue = JsValue::from_serde(&tree).unwrap();
+  let diagnostics_js: JsValue = JsValue::from_serde(&diagnostics).unwrap();
 
-  Ok(jsObj)
+  let result = js_sys::Array::new();
+  result.push(&tree_js);
+  result.push(&diagnostics_js);
+
+  Ok(result.into())
 }
 
-pub fn parse_to_json(contents: String) -> Result<String, Box<dyn Error>> {
-  let mut all_nodes = parse_into_nodes(contents)?;
-  let mut dummy_root = Node::new(-1, "dummy", "", "");
-  build_tree(&mut all_nodes, &mut dummy_root)?;
-  let json_string = serde_json::to_string_pretty(&dummy_root.children)
-    .unwrap()
-    .to_owned();
+pub fn parse_to_json(contents: String) -> Result<(String, Vec<Diagnostic>), BoxError> {
+  let (tree, diagnostics) = parse_to_tree(contents)?;
+  let json_string = serde_json::to_string_pretty(&tree).unwrap().to_owned();
 
-  Ok(json_string)
+  Ok((json_string, diagnostics))
 }
 
-macro_rules! asstr {
-  () => {
-    |m| m.as_str()
-  };
+#[wasm_bindgen]
+pub fn parse_to_js_resolved(contents: String) -> Result<JsValue, JsValue> {
+  let (tree, mut diagnostics) = parse_to_tree(contents).unwrap();
+  let resolved = resolve::resolve_tree(&tree, &mut diagnostics);
+
+  let tree_js: JsValue = JsValue::from_serde(&resolved).unwrap();
+  let diagnostics_js: JsValue = JsValue::from_serde(&diagnostics).unwrap();
+
+  let result = js_sys::Array::new();
+  result.push(&tree_js);
+  result.push(&diagnostics_js);
+
+  Ok(result.into())
 }
 
-fn parse_into_nodes<'a>(contents: String) -> Result<Vec<Node<'a>>, Box<dyn Error>> {
-  let re = Regex::new(r"\s*(0|[1-9]+[0-9]*) (@[^@]+@ |\b)([A-Za-z0-9_]+)( [^\n\r]*|\b)").unwrap();
+/// Like `parse_to_json`, but resolves every `@xref@` pointer against the
+/// file's level-0 records first, so the resulting JSON is graph-shaped:
+/// individuals, families and sources are addressable by id and
+/// relationships (spouse, child, parent, ...) are directly navigable.
+pub fn parse_to_json_resolved(contents: String) -> Result<(String, Vec<Diagnostic>), BoxError> {
+  let (tree, mut diagnostics) = parse_to_tree(contents)?;
+  let resolved = resolve::resolve_tree(&tree, &mut diagnostics);
+  let json_string = serde_json::to_string_pretty(&resolved).unwrap().to_owned();
 
-  let mut all_nodes = Vec::new();
-  let splitted_str = contents.split("\n");
-  for line in splitted_str {
-    for cap in re.captures_iter(line) {
-      let level: i32 = cap.get(1).unwrap().as_str().parse()?;
-      let pointer = cap.get(2).map_or("", asstr!());
-      let tag = cap.get(3).map_or("", asstr!());
-      let data = cap.get(4).map_or("", asstr!()).trim();
-      let new_node = Node::new(level, tag, data, pointer);
-
-      all_nodes.push(new_node);
+  Ok((json_string, diagnostics))
+}
+
+#[wasm_bindgen]
+pub fn json_to_gedcom_js(json: String) -> Result<JsValue, JsValue> {
+  let gedcom = json_to_gedcom(json).map_err(|err| JsValue::from_str(&err.to_string()))?;
+
+  Ok(JsValue::from_str(&gedcom))
+}
+
+/// The inverse of `parse_to_json`: deserializes the same `Node` tree
+/// JSON this crate produces and re-emits it as GEDCOM text, so a caller
+/// can read GEDCOM as JSON, edit it, and write it back out.
+pub fn json_to_gedcom(json: String) -> Result<String, BoxError> {
+  let tree: Vec<Node> = serde_json::from_str(&json)?;
+
+  Ok(writer::tree_to_gedcom(&tree))
+}
+
+type ChunkResult = Result<(Vec<Node>, Vec<Diagnostic>), BoxError>;
+
+fn parse_chunk((chunk, line_offset): (String, usize)) -> ChunkResult {
+  let (nodes, diagnostics) = parse_into_nodes(&chunk, line_offset)?;
+  Ok((build_tree(nodes), diagnostics))
+}
+
+/// Splits the file into level-0 record chunks and parses each chunk
+/// independently, then stitches the resulting subtrees back together in
+/// their original order. On native targets the chunks are parsed in
+/// parallel with rayon; rayon's thread pool needs `std::thread::spawn`,
+/// which `wasm32-unknown-unknown` doesn't have without a bootstrapped
+/// worker-pool shim, so the wasm build falls back to parsing serially.
+fn parse_to_tree(contents: String) -> Result<(Vec<Node>, Vec<Diagnostic>), BoxError> {
+  let chunks = split_into_record_chunks(&contents);
+
+  #[cfg(not(target_arch = "wasm32"))]
+  let results: Vec<ChunkResult> = chunks.into_par_iter().map(parse_chunk).collect();
+
+  #[cfg(target_arch = "wasm32")]
+  let results: Vec<ChunkResult> = chunks.into_iter().map(parse_chunk).collect();
+
+  let mut tree = Vec::new();
+  let mut diagnostics = Vec::new();
+  for result in results {
+    let (mut chunk_tree, mut chunk_diagnostics) = result?;
+    tree.append(&mut chunk_tree);
+    diagnostics.append(&mut chunk_diagnostics);
+  }
+
+  continuations::fold_tree(&mut tree);
+
+  Ok((tree, diagnostics))
+}
+
+/// Splits `contents` at every level-0 record boundary (`0 @...@ INDI`,
+/// `0 @...@ FAM`, the `0 HEAD`/`0 TRLR` lines, etc.), returning each
+/// chunk's text alongside the 0-based line number it starts at so
+/// diagnostics raised while parsing it still point at the right place
+/// in the original file.
+fn split_into_record_chunks(contents: &str) -> Vec<(String, usize)> {
+  let level0 = Regex::new(r"^\s*0(\s|$)").unwrap();
+
+  let mut chunks: Vec<(String, usize)> = Vec::new();
+  for (line_idx, line) in contents.split('\n').enumerate() {
+    if chunks.is_empty() || level0.is_match(line) {
+      chunks.push((String::new(), line_idx));
+    }
+
+    let (chunk, _) = chunks.last_mut().unwrap();
+    if !chunk.is_empty() {
+      chunk.push('\n');
     }
+    chunk.push_str(line);
   }
 
-  Ok(all_nodes)
+  chunks
 }
 
-fn build_tree<'a>(
-  ordered_nodes: &'a mut Vec<Node<'a>>,
-  dummy_root: &mut Node<'a>,
-) -> Result<(), Box<dyn Error>> {
-  let mut stack: Vec<&Node> = Vec::new();
-  let iter = ordered_nodes.iter_mut();
+fn parse_into_nodes(
+  contents: &str,
+  line_offset: usize,
+) -> Result<(Vec<Node>, Vec<Diagnostic>), BoxError> {
+  let mut all_nodes = Vec::new();
+  let mut diagnostics = Vec::new();
+  // Tracks the chain of ancestor levels seen so far, the same way
+  // `build_tree` walks a stack of open parents, so we can flag a child
+  // that jumps more than one level past its nearest open ancestor.
+  let mut level_stack: Vec<i32> = vec![-1];
 
-  stack.push(dummy_root);
+  for (line_idx, line) in contents.split('\n').enumerate() {
+    let line_no = line_offset + line_idx + 1;
 
-  for node in iter {
-    let popped = stack.pop();
+    if line.trim().is_empty() {
+      continue;
+    }
 
-    if let Some(mut popped) = popped {
-      while popped.level >= node.level {
-        popped = stack.pop().unwrap();
-      }
+    match lex_line(line) {
+      Ok(lexed) => {
+        while *level_stack.last().unwrap() >= lexed.level {
+          level_stack.pop();
+        }
+        let parent_level = *level_stack.last().unwrap();
+        if lexed.level - parent_level > 1 {
+          diagnostics.push(Diagnostic::warning(
+            format!(
+              "level {} jumps past its parent level {}; expected at most {}",
+              lexed.level,
+              parent_level,
+              parent_level + 1
+            ),
+            Span::single_line(line_no, lexed.start_column + 1, lexed.end_column + 1),
+          ));
+        }
+        level_stack.push(lexed.level);
 
-      if popped.level == node.level - 1 {
-        popped.children.borrow_mut().push(node);
+        let new_node = Node::new(
+          lexed.level,
+          &lexed.tag,
+          &lexed.value,
+          &lexed.xref,
+          line_no,
+          lexed.value_column,
+        );
+        all_nodes.push(new_node);
+      }
+      Err(LexError::MalformedXref { column }) => {
+        diagnostics.push(Diagnostic::error(
+          "malformed `@xref@` cross-reference pointer",
+          Span::single_line(line_no, column, line.len() + 1),
+        ));
+      }
+      Err(LexError::NoMatch) => {
+        diagnostics.push(Diagnostic::error(
+          "line does not match the `level pointer tag data` grammar",
+          Span::single_line(line_no, 1, line.len() + 1),
+        ));
       }
+    }
+  }
+
+  Ok((all_nodes, diagnostics))
+}
+
+/// Folds a flat, level-ordered list of nodes (as produced by
+/// `parse_into_nodes` for one record chunk) into an owned tree, using a
+/// stack of in-progress parents instead of borrowed references so the
+/// result has no lifetime tied to the input and can cross thread
+/// boundaries freely.
+fn build_tree(nodes: Vec<Node>) -> Vec<Node> {
+  let mut stack: Vec<Node> = vec![Node::new(-1, "dummy", "", "", 0, 0)];
 
-      stack.push(popped);
-      stack.push(node);
+  for node in nodes {
+    while stack.last().unwrap().level >= node.level {
+      let finished = stack.pop().unwrap();
+      stack.last_mut().unwrap().children.push(finished);
     }
+    stack.push(node);
   }
 
-  Ok(())
+  while stack.len() > 1 {
+    let finished = stack.pop().unwrap();
+    stack.last_mut().unwrap().children.push(finished);
+  }
+
+  stack.pop().unwrap().children
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn flags_lines_that_do_not_match_the_grammar() {
+    let (nodes, diagnostics) = parse_into_nodes("not a gedcom line\n", 0).unwrap();
+
+    assert!(nodes.is_empty());
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].level, Level::Error);
+    assert!(diagnostics[0].message.contains("grammar"));
+    assert_eq!(diagnostics[0].span.line_start, 1);
+  }
+
+  #[test]
+  fn flags_malformed_xref_pointers() {
+    let (nodes, diagnostics) = parse_into_nodes("0 @I1 INDI\n", 0).unwrap();
+
+    assert!(nodes.is_empty());
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].level, Level::Error);
+    assert!(diagnostics[0].message.contains("xref"));
+  }
+
+  #[test]
+  fn flags_level_jumps_past_the_nearest_parent() {
+    let (nodes, diagnostics) = parse_into_nodes("0 HEAD\n2 SOUR gedcom-2-json\n", 0).unwrap();
+
+    assert_eq!(nodes.len(), 2);
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].level, Level::Warning);
+    assert!(diagnostics[0].message.contains("jumps"));
+    assert_eq!(diagnostics[0].span.line_start, 2);
+  }
+
+  #[test]
+  fn well_formed_lines_raise_no_diagnostics() {
+    let (nodes, diagnostics) = parse_into_nodes("0 @I1@ INDI\n1 NAME John /Doe/\n", 0).unwrap();
+
+    assert_eq!(nodes.len(), 2);
+    assert!(diagnostics.is_empty());
+  }
+
+  #[test]
+  fn parse_to_tree_preserves_record_order_across_chunks() {
+    let contents = "0 HEAD\n\
+      0 @I1@ INDI\n\
+      1 NAME John /Doe/\n\
+      0 @I2@ INDI\n\
+      1 NAME Jane /Doe/\n\
+      0 TRLR\n"
+      .to_string();
+
+    let (tree, diagnostics) = parse_to_tree(contents).unwrap();
+    assert!(diagnostics.is_empty());
+
+    let tags: Vec<&str> = tree.iter().map(|node| node.tag.as_str()).collect();
+    assert_eq!(tags, vec!["HEAD", "INDI", "INDI", "TRLR"]);
+
+    assert_eq!(tree[1].pointer, "@I1@");
+    assert_eq!(tree[1].children[0].tag, "NAME");
+    assert_eq!(tree[1].children[0].data, "John /Doe/");
+
+    assert_eq!(tree[2].pointer, "@I2@");
+    assert_eq!(tree[2].children[0].data, "Jane /Doe/");
+  }
+
+  #[test]
+  fn build_tree_nests_children_by_level() {
+    let nodes = parse_into_nodes(
+      "0 @I1@ INDI\n1 NAME John /Doe/\n2 GIVN John\n1 SEX M\n",
+      0,
+    )
+    .unwrap()
+    .0;
+
+    let tree = build_tree(nodes);
+
+    assert_eq!(tree.len(), 1);
+    let indi = &tree[0];
+    assert_eq!(indi.children.len(), 2);
+    assert_eq!(indi.children[0].tag, "NAME");
+    assert_eq!(indi.children[0].children[0].tag, "GIVN");
+    assert_eq!(indi.children[1].tag, "SEX");
+  }
 }
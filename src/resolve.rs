@@ -0,0 +1,163 @@
+use crate::diagnostics::{Diagnostic, Span};
+use crate::Node;
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// A pointer value (e.g. `@I1@`) resolved against the level-0 record it
+/// targets, so callers can follow a relationship without re-scanning the
+/// whole tree for a matching xref.
+#[derive(Debug, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum ResolvedPointer {
+  /// The pointer's target was found among the level-0 records.
+  Record { xref: String, record_type: String },
+  /// The pointer's target xref does not exist anywhere in the file.
+  Dangling { xref: String },
+}
+
+/// A `Node` whose pointer-valued `data` has been resolved, turning the
+/// tree into a graph-shaped structure where individuals, families and
+/// sources are addressable by xref and relationships are navigable.
+#[derive(Debug, Serialize)]
+pub struct ResolvedNode {
+  pub tag: String,
+  pub data: String,
+  pub pointer: String,
+  pub level: i32,
+  pub reference: Option<ResolvedPointer>,
+  pub children: Vec<ResolvedNode>,
+}
+
+/// Indexes every level-0 record by its xref id, then walks `tree`
+/// resolving any node whose `data` is an `@xref@` pointer against that
+/// index. Dangling pointers (no matching level-0 record) are reported
+/// through `diagnostics` instead of failing the resolve pass.
+pub fn resolve_tree(tree: &[Node], diagnostics: &mut Vec<Diagnostic>) -> Vec<ResolvedNode> {
+  let index = index_records(tree);
+  tree
+    .iter()
+    .map(|node| resolve_node(node, &index, diagnostics))
+    .collect()
+}
+
+fn index_records(tree: &[Node]) -> HashMap<String, &Node> {
+  let mut index = HashMap::new();
+  for record in tree {
+    let xref = xref_id(&record.pointer);
+    if !xref.is_empty() {
+      index.insert(xref, record);
+    }
+  }
+  index
+}
+
+fn xref_id(pointer: &str) -> String {
+  pointer.trim().trim_matches('@').to_string()
+}
+
+fn is_pointer(data: &str) -> bool {
+  // `@#DHEBREW@`-style escapes (calendar/language markers) are wrapped in
+  // `@...@` too but aren't cross-reference pointers.
+  data.len() > 2 && data.starts_with('@') && !data.starts_with("@#") && data.ends_with('@')
+}
+
+fn resolve_node(
+  node: &Node,
+  index: &HashMap<String, &Node>,
+  diagnostics: &mut Vec<Diagnostic>,
+) -> ResolvedNode {
+  let reference = if is_pointer(&node.data) {
+    let xref = xref_id(&node.data);
+    Some(match index.get(xref.as_str()) {
+      Some(target) => ResolvedPointer::Record {
+        xref,
+        record_type: target.tag.clone(),
+      },
+      None => {
+        diagnostics.push(Diagnostic::error(
+          format!(
+            "`{}` references xref `@{}@`, which has no matching record",
+            node.tag, xref
+          ),
+          Span::single_line(
+            node.line,
+            node.column + 1,
+            node.column + 1 + node.data.len(),
+          ),
+        ));
+        ResolvedPointer::Dangling { xref }
+      }
+    })
+  } else {
+    None
+  };
+
+  ResolvedNode {
+    tag: node.tag.clone(),
+    data: node.data.clone(),
+    pointer: node.pointer.clone(),
+    level: node.level,
+    reference,
+    children: node
+      .children
+      .iter()
+      .map(|child| resolve_node(child, index, diagnostics))
+      .collect(),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn resolves_a_pointer_against_its_matching_record() {
+    let mut fam = Node::new(0, "FAM", "", "@F1@", 1, 0);
+    fam.children.push(Node::new(1, "HUSB", "@I1@", "", 2, 0));
+    let tree = vec![Node::new(0, "INDI", "", "@I1@", 1, 0), fam];
+
+    let mut diagnostics = Vec::new();
+    let resolved = resolve_tree(&tree, &mut diagnostics);
+
+    assert!(diagnostics.is_empty());
+    let husb = &resolved[1].children[0];
+    match husb.reference.as_ref().unwrap() {
+      ResolvedPointer::Record { xref, record_type } => {
+        assert_eq!(xref, "I1");
+        assert_eq!(record_type, "INDI");
+      }
+      other => panic!("expected a resolved record, got {:?}", other),
+    }
+  }
+
+  #[test]
+  fn reports_a_dangling_pointer() {
+    let mut fam = Node::new(0, "FAM", "", "@F1@", 1, 0);
+    fam.children.push(Node::new(1, "HUSB", "@I1@", "", 2, 5));
+    let tree = vec![fam];
+
+    let mut diagnostics = Vec::new();
+    let resolved = resolve_tree(&tree, &mut diagnostics);
+
+    assert_eq!(diagnostics.len(), 1);
+    assert!(diagnostics[0].message.contains("@I1@"));
+    assert_eq!(diagnostics[0].span.line_start, 2);
+    assert_eq!(diagnostics[0].span.column_start, 6);
+
+    match resolved[0].children[0].reference.as_ref().unwrap() {
+      ResolvedPointer::Dangling { xref } => assert_eq!(xref, "I1"),
+      other => panic!("expected a dangling reference, got {:?}", other),
+    }
+  }
+
+  #[test]
+  fn does_not_treat_an_escape_as_a_pointer() {
+    let node = Node::new(1, "DATE", "@#DHEBREW@ 01 TSH 5481", "", 1, 0);
+
+    let mut diagnostics = Vec::new();
+    let resolved = resolve_tree(&[node], &mut diagnostics);
+
+    assert!(diagnostics.is_empty());
+    assert!(resolved[0].reference.is_none());
+  }
+}
@@ -0,0 +1,149 @@
+//! A small hand-rolled lexer for single GEDCOM lines, replacing the
+//! original catch-all regex. Each line is tokenized into its typed
+//! pieces (level, xref, tag, line value) instead of being matched
+//! against one monolithic pattern, which makes it possible to report
+//! exactly where a line stops conforming to the grammar.
+
+/// The typed pieces read from one line, plus 0-based columns in the
+/// original line (used for diagnostic spans): `start_column..end_column`
+/// covers the level, xref and tag, and `value_column` is where the value
+/// starts.
+#[derive(Debug, Clone)]
+pub struct LexedLine {
+  pub level: i32,
+  pub xref: String,
+  pub tag: String,
+  pub value: String,
+  pub start_column: usize,
+  /// 0-based column where the (trimmed) `value` starts in the line.
+  pub value_column: usize,
+  pub end_column: usize,
+}
+
+/// Why a line could not be tokenized as `level [pointer] tag [value]`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LexError {
+  /// The line doesn't start with a level number, or has no tag.
+  NoMatch,
+  /// An xref's opening `@` was never closed.
+  MalformedXref { column: usize },
+}
+
+/// Tokenizes a single line (with no trailing `\n`) into its GEDCOM
+/// pieces. BOM and surrounding whitespace are skipped; blank lines are
+/// the caller's responsibility to filter out before calling this.
+pub fn lex_line(line: &str) -> Result<LexedLine, LexError> {
+  let line = line.trim_end_matches('\r');
+  let start_column = line.len() - line.trim_start_matches(is_line_whitespace).len();
+  let rest = &line[start_column..];
+
+  let digits_len = rest.find(|c: char| !c.is_ascii_digit()).unwrap_or(rest.len());
+  if digits_len == 0 {
+    return Err(LexError::NoMatch);
+  }
+  let level: i32 = rest[..digits_len].parse().map_err(|_| LexError::NoMatch)?;
+
+  if rest.as_bytes().get(digits_len) != Some(&b' ') {
+    return Err(LexError::NoMatch);
+  }
+  let mut remainder = &rest[digits_len + 1..];
+  let mut consumed = digits_len + 1;
+
+  let mut xref = String::new();
+  if remainder.starts_with('@') {
+    match remainder[1..].find('@') {
+      Some(rel_end) => {
+        let xref_end = 1 + rel_end + 1;
+        xref.push_str(&remainder[..xref_end]);
+        consumed += xref_end;
+        remainder = &remainder[xref_end..];
+        if remainder.starts_with(' ') {
+          consumed += 1;
+          remainder = &remainder[1..];
+        }
+      }
+      None => {
+        return Err(LexError::MalformedXref {
+          column: start_column + consumed + 1,
+        });
+      }
+    }
+  }
+
+  let tag_len = remainder
+    .find(|c: char| c.is_whitespace())
+    .unwrap_or(remainder.len());
+  if tag_len == 0 || !remainder[..tag_len].chars().all(is_tag_char) {
+    return Err(LexError::NoMatch);
+  }
+  let tag = remainder[..tag_len].to_string();
+  consumed += tag_len;
+
+  let raw_value = &remainder[tag_len..];
+  let value_leading_ws = raw_value.len() - raw_value.trim_start().len();
+  let value = raw_value.trim().to_string();
+  let value_column = start_column + consumed + value_leading_ws;
+
+  Ok(LexedLine {
+    level,
+    xref,
+    tag,
+    value,
+    start_column,
+    value_column,
+    end_column: start_column + consumed,
+  })
+}
+
+fn is_line_whitespace(c: char) -> bool {
+  c.is_whitespace() || c == '\u{FEFF}'
+}
+
+fn is_tag_char(c: char) -> bool {
+  c.is_ascii_alphanumeric() || c == '_'
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn lexes_a_plain_line() {
+    let lexed = lex_line("1 NAME John /Doe/").unwrap();
+
+    assert_eq!(lexed.level, 1);
+    assert_eq!(lexed.xref, "");
+    assert_eq!(lexed.tag, "NAME");
+    assert_eq!(lexed.value, "John /Doe/");
+    assert_eq!(lexed.start_column, 0);
+    assert_eq!(lexed.value_column, "1 NAME ".len());
+  }
+
+  #[test]
+  fn lexes_a_line_with_an_xref_and_no_value() {
+    let lexed = lex_line("0 @I1@ INDI").unwrap();
+
+    assert_eq!(lexed.level, 0);
+    assert_eq!(lexed.xref, "@I1@");
+    assert_eq!(lexed.tag, "INDI");
+    assert_eq!(lexed.value, "");
+  }
+
+  #[test]
+  fn rejects_a_line_with_no_level() {
+    assert_eq!(lex_line("NOTE hello").unwrap_err(), LexError::NoMatch);
+  }
+
+  #[test]
+  fn rejects_a_line_with_no_tag() {
+    assert_eq!(lex_line("1 ").unwrap_err(), LexError::NoMatch);
+  }
+
+  #[test]
+  fn reports_the_column_of_an_unclosed_xref() {
+    match lex_line("0 @I1 INDI").unwrap_err() {
+      LexError::MalformedXref { column } => assert_eq!(column, 3),
+      other => panic!("expected MalformedXref, got {:?}", other),
+    }
+  }
+}
@@ -0,0 +1,106 @@
+use crate::Node;
+
+// GEDCOM lines are conventionally kept to 255 characters; leave enough
+// headroom for the `level CONC ` prefix on continuation lines.
+const MAX_VALUE_LEN: usize = 248;
+
+/// Re-emits a `Node` tree as GEDCOM text, the inverse of
+/// `parse_into_nodes`/`build_tree` (plus `continuations::fold_tree`):
+/// each node becomes a `level [pointer] tag [data]` line, with embedded
+/// newlines in `data` (folded from `CONT` children) split back into
+/// `CONT` lines, and any line that's still too long folded into `CONC`
+/// continuation lines.
+pub fn tree_to_gedcom(tree: &[Node]) -> String {
+  let mut lines = Vec::new();
+  for node in tree {
+    write_node(node, &mut lines);
+  }
+  lines.join("\n")
+}
+
+fn write_node(node: &Node, lines: &mut Vec<String>) {
+  // `CONC`/`CONT` are always siblings one level below the tag line they
+  // continue, never nested under each other, so every continuation for
+  // this node — whether folding the first segment or a later `CONT`
+  // segment — shares this one level.
+  let continuation_level = node.level + 1;
+
+  let mut line_values = node.data.split('\n');
+
+  let first_segment = line_values.next().unwrap_or_default();
+  write_line_value(
+    node.level,
+    continuation_level,
+    &node.pointer,
+    &node.tag,
+    first_segment,
+    lines,
+  );
+
+  for segment in line_values {
+    write_line_value(continuation_level, continuation_level, "", "CONT", segment, lines);
+  }
+
+  for child in &node.children {
+    write_node(child, lines);
+  }
+}
+
+/// Emits `segment` as one `tag` line at `level`, folding any overflow
+/// past `MAX_VALUE_LEN` into `CONC` lines at `continuation_level`.
+fn write_line_value(
+  level: i32,
+  continuation_level: i32,
+  pointer: &str,
+  tag: &str,
+  segment: &str,
+  lines: &mut Vec<String>,
+) {
+  let mut chunks = split_into_line_values(segment).into_iter();
+  let first_chunk = chunks.next().unwrap_or_default();
+
+  lines.push(format_line(level, pointer, tag, &first_chunk));
+
+  for chunk in chunks {
+    lines.push(format_line(continuation_level, "", "CONC", &chunk));
+  }
+}
+
+fn format_line(level: i32, pointer: &str, tag: &str, data: &str) -> String {
+  let mut parts = vec![level.to_string()];
+
+  let pointer = pointer.trim();
+  if !pointer.is_empty() {
+    parts.push(pointer.to_string());
+  }
+
+  parts.push(tag.to_string());
+
+  if !data.is_empty() {
+    parts.push(data.to_string());
+  }
+
+  parts.join(" ")
+}
+
+fn split_into_line_values(data: &str) -> Vec<String> {
+  if data.is_empty() {
+    return vec![String::new()];
+  }
+
+  let chars: Vec<char> = data.chars().collect();
+  let mut values = Vec::new();
+  let mut start = 0;
+  while start < chars.len() {
+    let hard_end = (start + MAX_VALUE_LEN).min(chars.len());
+    // Line values are trimmed when read back, so don't split next to
+    // whitespace unless the chunk offers no other place to split.
+    let end = (start + 1..=hard_end)
+      .rev()
+      .find(|&end| end == chars.len() || !(chars[end - 1].is_whitespace() || chars[end].is_whitespace()))
+      .unwrap_or(hard_end);
+    values.push(chars[start..end].iter().collect());
+    start = end;
+  }
+  values
+}
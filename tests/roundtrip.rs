@@ -0,0 +1,37 @@
+use gedcom_parser::{json_to_gedcom, parse_to_json};
+
+const FIXTURE: &str = include_str!("fixtures/family.ged");
+const LONG_NOTE_FIXTURE: &str = include_str!("fixtures/long_note.ged");
+
+#[test]
+fn gedcom_json_gedcom_round_trip_is_stable() {
+  let (json, diagnostics) = parse_to_json(FIXTURE.to_string()).unwrap();
+  assert!(diagnostics.is_empty(), "unexpected diagnostics: {:?}", diagnostics);
+
+  let gedcom = json_to_gedcom(json.clone()).unwrap();
+  assert_eq!(gedcom, FIXTURE.trim_end());
+
+  let (json_again, diagnostics_again) = parse_to_json(gedcom).unwrap();
+  assert!(diagnostics_again.is_empty());
+  assert_eq!(json, json_again);
+}
+
+/// A `CONT` segment longer than the writer's `CONC` fold threshold must
+/// come back out at the same level as the `CONT` line itself (a sibling
+/// of it under the same parent), not progressively nested one level
+/// deeper per continuation — otherwise re-parsing the written GEDCOM
+/// drops the overflow and silently truncates the value.
+#[test]
+fn long_continuation_values_survive_two_round_trips() {
+  let (json, diagnostics) = parse_to_json(LONG_NOTE_FIXTURE.to_string()).unwrap();
+  assert!(diagnostics.is_empty(), "unexpected diagnostics: {:?}", diagnostics);
+
+  let gedcom = json_to_gedcom(json.clone()).unwrap();
+  let (json_again, diagnostics_again) = parse_to_json(gedcom.clone()).unwrap();
+  assert!(diagnostics_again.is_empty());
+  assert_eq!(json, json_again, "note content changed across one round trip");
+
+  // Writing out an already-canonical tree should be a fixed point.
+  let gedcom_again = json_to_gedcom(json_again).unwrap();
+  assert_eq!(gedcom, gedcom_again);
+}